@@ -0,0 +1,44 @@
+use std::net::SocketAddr;
+use std::thread::{self, JoinHandle};
+
+use tiny_http::{Header, Response, Server};
+
+use crate::get_metrics;
+
+/// Starts a background HTTP server that serves the Prometheus scrape endpoint
+/// at `/metrics` on `addr`. Requests to any other path get a 404.
+pub fn serve_prometheus(addr: SocketAddr) -> ExporterHandle {
+    let server = Server::http(addr).expect("failed to bind prometheus exporter");
+    let thread = thread::spawn(move || {
+        for request in server.incoming_requests() {
+            if request.url() != "/metrics" {
+                let _ = request.respond(Response::empty(404));
+                continue;
+            }
+            let report = {
+                // NOTE: minimize lock in scope
+                let registry = get_metrics()
+                    .read()
+                    .expect("registry must be valid to read");
+                registry.encode_prometheus_report()
+            };
+            let content_type =
+                Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..])
+                    .expect("static header is valid");
+            let response = Response::from_string(report).with_header(content_type);
+            let _ = request.respond(response);
+        }
+    });
+    ExporterHandle { thread }
+}
+
+/// Join guard for the background thread started by `serve_prometheus`.
+pub struct ExporterHandle {
+    thread: JoinHandle<()>,
+}
+
+impl ExporterHandle {
+    pub fn join(self) {
+        let _ = self.thread.join();
+    }
+}
@@ -1,9 +1,16 @@
 use std::collections::BTreeMap;
-use std::sync::{OnceLock, RwLock};
-use std::sync::atomic::{AtomicPtr, Ordering};
+use std::fmt;
+use std::sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock, RwLock};
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 
+#[cfg(feature = "http")]
+mod exporter;
+
+#[cfg(feature = "http")]
+pub use exporter::{serve_prometheus, ExporterHandle};
+
 pub struct Counter {
     state: Box<State>,
 }
@@ -18,30 +25,49 @@ impl Counter {
         keys: [&str; N],
         labels: [impl ToString; N],
     ) -> Self {
-        let labels = format_labels(keys, labels.map(|v| v.to_string()));
+        let labels = format_label_pairs(keys, labels.map(|v| v.to_string()));
         let mut registry = get_metrics().write().expect("registry must be valid");
         let counter = State {
             name: name.to_string(),
-            key: format!("{name}{labels}"),
+            key: format!("{name}{}", braced(&labels)),
             help: String::new(),
-            value: Default::default(),
+            value: AtomicU64::new(0),
             kind: Kind::Counter,
+            labels,
+            buckets: Vec::new(),
+            bucket_counts: Vec::new(),
+            sum: AtomicU64::new(0),
+            count: AtomicUsize::new(0),
+            updated_at: AtomicU64::new(now_nanos()),
+            unit: None,
+            epsilon: 0.0,
+            quantiles: Vec::new(),
+            sketch: Mutex::new(Vec::new()),
         };
         let mut state = Box::new(counter);
         registry.register(&mut state);
         Self { state }
     }
 
-    pub fn inc(&mut self) {
+    pub fn inc(&self) {
         self.add(1);
     }
 
-    pub fn add(&mut self, value: usize) {
-        self.state.value += value as f64;
+    pub fn add(&self, value: usize) {
+        let scale = scale_of(&self.state);
+        update_f64(&self.state.value, |v| v + value as f64 * scale);
+        touch(&self.state);
     }
 
     pub fn value(&self) -> usize {
-        self.state.value as usize
+        load_f64(&self.state.value) as usize
+    }
+
+    /// Declares the unit this counter is measured in, appending its canonical
+    /// suffix (e.g. `_seconds`) to the metric name if not already present.
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.state = apply_unit(self.state, unit);
+        self
     }
 }
 
@@ -59,14 +85,24 @@ impl Gauge {
         keys: [&str; N],
         labels: [impl ToString; N],
     ) -> Self {
-        let labels = format_labels(keys, labels.map(|v| v.to_string()));
+        let labels = format_label_pairs(keys, labels.map(|v| v.to_string()));
         let mut registry = get_metrics().write().expect("registry must be valid");
         let counter = State {
             name: name.to_string(),
-            key: format!("{name}{labels}"),
+            key: format!("{name}{}", braced(&labels)),
             help: String::new(),
-            value: Default::default(),
+            value: AtomicU64::new(0),
             kind: Kind::Gauge,
+            labels,
+            buckets: Vec::new(),
+            bucket_counts: Vec::new(),
+            sum: AtomicU64::new(0),
+            count: AtomicUsize::new(0),
+            updated_at: AtomicU64::new(now_nanos()),
+            unit: None,
+            epsilon: 0.0,
+            quantiles: Vec::new(),
+            sketch: Mutex::new(Vec::new()),
         };
         let mut state = Box::new(counter);
         registry.register(&mut state);
@@ -74,96 +110,488 @@ impl Gauge {
     }
 
     pub fn value(&self) -> f64 {
-        self.state.value
+        load_f64(&self.state.value)
+    }
+
+    /// Raises the gauge to `value` if it is currently lower, for high-water marks
+    /// like peak queue depth. The value is left unchanged if it is already
+    /// greater, but the call still refreshes the idle-timeout update time.
+    pub fn set_max(&self, value: f64) {
+        let value = value * scale_of(&self.state);
+        update_f64(&self.state.value, |current| current.max(value));
+        touch(&self.state);
+    }
+
+    /// Declares the unit this gauge is measured in, appending its canonical
+    /// suffix (e.g. `_seconds`) to the metric name if not already present.
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.state = apply_unit(self.state, unit);
+        self
     }
 }
 
 pub trait GaugeValue<T> {
-    fn set(&mut self, value: T);
-    fn add(&mut self, value: T);
+    fn set(&self, value: T);
+    fn add(&self, value: T);
 }
 
 impl GaugeValue<Instant> for Gauge {
-    fn set(&mut self, value: Instant) {
-        self.state.value = value.elapsed().as_secs_f64();
+    fn set(&self, value: Instant) {
+        store_f64(
+            &self.state.value,
+            value.elapsed().as_secs_f64() * scale_of(&self.state),
+        );
+        touch(&self.state);
     }
 
-    fn add(&mut self, value: Instant) {
-        self.state.value += value.elapsed().as_secs_f64();
+    fn add(&self, value: Instant) {
+        let elapsed = value.elapsed().as_secs_f64() * scale_of(&self.state);
+        update_f64(&self.state.value, |v| v + elapsed);
+        touch(&self.state);
     }
 }
 
 impl GaugeValue<&mut Stopwatch> for Gauge {
-    fn set(&mut self, value: &mut Stopwatch) {
-        self.state.value = value.lap().elapsed().as_secs_f64();
+    fn set(&self, value: &mut Stopwatch) {
+        store_f64(
+            &self.state.value,
+            value.lap().elapsed().as_secs_f64() * scale_of(&self.state),
+        );
+        touch(&self.state);
     }
 
-    fn add(&mut self, value: &mut Stopwatch) {
-        self.state.value += value.lap().elapsed().as_secs_f64();
+    fn add(&self, value: &mut Stopwatch) {
+        let elapsed = value.lap().elapsed().as_secs_f64() * scale_of(&self.state);
+        update_f64(&self.state.value, |v| v + elapsed);
+        touch(&self.state);
     }
 }
 
 impl GaugeValue<usize> for Gauge {
-    fn set(&mut self, value: usize) {
-        self.state.value = value as f64;
+    fn set(&self, value: usize) {
+        store_f64(&self.state.value, value as f64 * scale_of(&self.state));
+        touch(&self.state);
     }
 
-    fn add(&mut self, value: usize) {
-        self.state.value += value as f64;
+    fn add(&self, value: usize) {
+        let scaled = value as f64 * scale_of(&self.state);
+        update_f64(&self.state.value, |v| v + scaled);
+        touch(&self.state);
     }
 }
 
 impl GaugeValue<i32> for Gauge {
-    fn set(&mut self, value: i32) {
-        self.state.value = value as f64;
+    fn set(&self, value: i32) {
+        store_f64(&self.state.value, value as f64 * scale_of(&self.state));
+        touch(&self.state);
     }
 
-    fn add(&mut self, value: i32) {
-        self.state.value += value as f64;
+    fn add(&self, value: i32) {
+        let scaled = value as f64 * scale_of(&self.state);
+        update_f64(&self.state.value, |v| v + scaled);
+        touch(&self.state);
     }
 }
 
 impl GaugeValue<f32> for Gauge {
-    fn set(&mut self, value: f32) {
-        self.state.value = value as f64;
+    fn set(&self, value: f32) {
+        store_f64(&self.state.value, value as f64 * scale_of(&self.state));
+        touch(&self.state);
     }
 
-    fn add(&mut self, value: f32) {
-        self.state.value += value as f64;
+    fn add(&self, value: f32) {
+        let scaled = value as f64 * scale_of(&self.state);
+        update_f64(&self.state.value, |v| v + scaled);
+        touch(&self.state);
+    }
+}
+
+pub struct Histogram {
+    state: Box<State>,
+}
+
+impl Histogram {
+    pub fn new(name: &str) -> Self {
+        Self::with_labels(name, [], [0; 0])
+    }
+
+    pub fn with_labels<const N: usize>(
+        name: &str,
+        keys: [&str; N],
+        labels: [impl ToString; N],
+    ) -> Self {
+        Self::with_buckets(name, keys, labels, default_buckets())
+    }
+
+    pub fn with_buckets<const N: usize>(
+        name: &str,
+        keys: [&str; N],
+        labels: [impl ToString; N],
+        mut buckets: Vec<f64>,
+    ) -> Self {
+        buckets.sort_by(|a, b| a.partial_cmp(b).expect("bucket bound must not be NaN"));
+        let labels = format_label_pairs(keys, labels.map(|v| v.to_string()));
+        let mut registry = get_metrics().write().expect("registry must be valid");
+        let bucket_counts = buckets.iter().map(|_| AtomicUsize::new(0)).collect();
+        let histogram = State {
+            name: name.to_string(),
+            key: format!("{name}{}", braced(&labels)),
+            help: String::new(),
+            value: AtomicU64::new(0),
+            kind: Kind::Histogram,
+            labels,
+            buckets,
+            bucket_counts,
+            sum: AtomicU64::new(0),
+            count: AtomicUsize::new(0),
+            updated_at: AtomicU64::new(now_nanos()),
+            unit: None,
+            epsilon: 0.0,
+            quantiles: Vec::new(),
+            sketch: Mutex::new(Vec::new()),
+        };
+        let mut state = Box::new(histogram);
+        registry.register(&mut state);
+        Self { state }
+    }
+}
+
+/// Default bucket ladder for frame-time style observations (~125, 60, 30, 15 fps).
+fn default_buckets() -> Vec<f64> {
+    vec![0.008, 0.016, 0.033, 0.066]
+}
+
+pub trait HistogramValue<T> {
+    fn observe(&self, value: T);
+}
+
+impl HistogramValue<f64> for Histogram {
+    fn observe(&self, value: f64) {
+        for (bound, count) in self
+            .state
+            .buckets
+            .iter()
+            .zip(self.state.bucket_counts.iter())
+        {
+            if value <= *bound {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        update_f64(&self.state.sum, |sum| sum + value);
+        self.state.count.fetch_add(1, Ordering::Relaxed);
+        touch(&self.state);
+    }
+}
+
+impl HistogramValue<Instant> for Histogram {
+    fn observe(&self, value: Instant) {
+        self.observe(value.elapsed().as_secs_f64());
+    }
+}
+
+impl HistogramValue<&mut Stopwatch> for Histogram {
+    fn observe(&self, value: &mut Stopwatch) {
+        self.observe(value.lap().elapsed().as_secs_f64());
+    }
+}
+
+/// Streaming quantile summary (CKMS/GK-style) for latencies whose bucket
+/// boundaries aren't known ahead of time. Maintains a bounded, compressed
+/// sketch of `(value, g, delta)` tuples ordered by value, where `g` is the
+/// gap in rank from the previous stored value and `delta` is the max rank
+/// error, so memory stays bounded regardless of how the observed values
+/// are distributed.
+pub struct Summary {
+    state: Box<State>,
+}
+
+impl Summary {
+    pub fn new(name: &str) -> Self {
+        Self::with_labels(name, [], [0; 0])
+    }
+
+    pub fn with_labels<const N: usize>(
+        name: &str,
+        keys: [&str; N],
+        labels: [impl ToString; N],
+    ) -> Self {
+        Self::with_quantiles(name, keys, labels, default_quantiles(), default_epsilon())
+    }
+
+    pub fn with_quantiles<const N: usize>(
+        name: &str,
+        keys: [&str; N],
+        labels: [impl ToString; N],
+        quantiles: Vec<f64>,
+        epsilon: f64,
+    ) -> Self {
+        let labels = format_label_pairs(keys, labels.map(|v| v.to_string()));
+        let mut registry = get_metrics().write().expect("registry must be valid");
+        let summary = State {
+            name: name.to_string(),
+            key: format!("{name}{}", braced(&labels)),
+            help: String::new(),
+            value: AtomicU64::new(0),
+            kind: Kind::Summary,
+            labels,
+            buckets: Vec::new(),
+            bucket_counts: Vec::new(),
+            sum: AtomicU64::new(0),
+            count: AtomicUsize::new(0),
+            updated_at: AtomicU64::new(now_nanos()),
+            unit: None,
+            epsilon,
+            quantiles,
+            sketch: Mutex::new(Vec::new()),
+        };
+        let mut state = Box::new(summary);
+        registry.register(&mut state);
+        Self { state }
+    }
+
+    /// Estimated value at quantile `phi` (e.g. `0.99`), accurate to within
+    /// `epsilon`. `None` if nothing has been observed yet.
+    pub fn quantile(&self, phi: f64) -> Option<f64> {
+        quantile_from_sketch(&self.state, phi)
+    }
+}
+
+fn default_quantiles() -> Vec<f64> {
+    vec![0.5, 0.9, 0.99]
+}
+
+fn default_epsilon() -> f64 {
+    0.01
+}
+
+fn quantile_from_sketch(state: &State, phi: f64) -> Option<f64> {
+    let sketch = state.sketch.lock().expect("sketch must be valid");
+    if sketch.is_empty() {
+        return None;
+    }
+    let n = state.count.load(Ordering::Relaxed) as f64;
+    let band = (2.0 * state.epsilon * n).floor();
+    let threshold = (phi * n).ceil() + band / 2.0;
+    let mut rank = 0.0;
+    for (value, g, delta) in sketch.iter() {
+        rank += g;
+        if rank + delta > threshold {
+            return Some(*value);
+        }
+    }
+    sketch.last().map(|(value, _, _)| *value)
+}
+
+pub trait SummaryValue<T> {
+    fn observe(&self, value: T);
+}
+
+impl SummaryValue<f64> for Summary {
+    fn observe(&self, value: f64) {
+        let n = self.state.count.load(Ordering::Relaxed) + 1;
+        let band = (2.0 * self.state.epsilon * n as f64).floor();
+        let mut sketch = self.state.sketch.lock().expect("sketch must be valid");
+        let position = sketch.partition_point(|(v, _, _)| *v < value);
+        let (g, delta) = if position == 0 || position == sketch.len() {
+            (1.0, 0.0)
+        } else {
+            (1.0, band)
+        };
+        sketch.insert(position, (value, g, delta));
+        compress(&mut sketch, band);
+        drop(sketch);
+        update_f64(&self.state.sum, |sum| sum + value);
+        self.state.count.fetch_add(1, Ordering::Relaxed);
+        touch(&self.state);
+    }
+}
+
+impl SummaryValue<Instant> for Summary {
+    fn observe(&self, value: Instant) {
+        self.observe(value.elapsed().as_secs_f64());
+    }
+}
+
+impl SummaryValue<&mut Stopwatch> for Summary {
+    fn observe(&self, value: &mut Stopwatch) {
+        self.observe(value.lap().elapsed().as_secs_f64());
+    }
+}
+
+/// Merges adjacent tuples whenever `g_i + g_{i+1} + delta_{i+1} <= band`,
+/// keeping the sketch's size bounded. The extremes (index `0` and the last
+/// tuple) are never merged away so the observed min/max stay exact.
+fn compress(sketch: &mut Vec<(f64, f64, f64)>, band: f64) {
+    if sketch.len() < 3 {
+        return;
+    }
+    let mut i = sketch.len() - 2;
+    loop {
+        let g = sketch[i].1;
+        let (next_g, next_delta) = (sketch[i + 1].1, sketch[i + 1].2);
+        if i > 0 && g + next_g + next_delta <= band {
+            sketch[i + 1].1 += g;
+            sketch.remove(i);
+        }
+        if i == 1 {
+            break;
+        }
+        i -= 1;
     }
 }
 
 enum Kind {
     Counter,
     Gauge,
+    Histogram,
+    Summary,
+}
+
+/// Unit a metric's value is measured in, following Prometheus naming conventions
+/// (https://prometheus.io/docs/practices/naming/#base-units). Byte scales are
+/// split into binary (`Kibibytes`, `Mebibytes`, `Gibibytes`, base 1024) and
+/// decimal (`Kilobytes`, `Megabytes`, `Gigabytes`, base 1000) variants so a
+/// caller can't accidentally conflate KiB with KB; `scale` converts a value in
+/// that unit to the base unit (bytes, seconds, ...) metrics should be stored in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Seconds,
+    Bytes,
+    Kibibytes,
+    Mebibytes,
+    Gibibytes,
+    Kilobytes,
+    Megabytes,
+    Gigabytes,
+    Count,
+    Percent,
+}
+
+impl Unit {
+    fn name(self) -> &'static str {
+        match self {
+            Unit::Seconds => "seconds",
+            Unit::Bytes
+            | Unit::Kibibytes
+            | Unit::Mebibytes
+            | Unit::Gibibytes
+            | Unit::Kilobytes
+            | Unit::Megabytes
+            | Unit::Gigabytes => "bytes",
+            Unit::Count => "count",
+            Unit::Percent => "percent",
+        }
+    }
+
+    /// Multiplier to convert a value expressed in this unit to the base unit
+    /// (bytes for byte scales) a metric's value should be recorded in.
+    pub fn scale(self) -> f64 {
+        match self {
+            Unit::Kibibytes => 1024.0,
+            Unit::Mebibytes => 1024.0 * 1024.0,
+            Unit::Gibibytes => 1024.0 * 1024.0 * 1024.0,
+            Unit::Kilobytes => 1_000.0,
+            Unit::Megabytes => 1_000_000.0,
+            Unit::Gigabytes => 1_000_000_000.0,
+            Unit::Seconds | Unit::Bytes | Unit::Count | Unit::Percent => 1.0,
+        }
+    }
 }
 
-struct State {
+pub struct State {
     name: String,
     key: String,
     help: String,
-    value: f64,
+    value: AtomicU64,
     kind: Kind,
+    labels: String,
+    buckets: Vec<f64>,
+    bucket_counts: Vec<AtomicUsize>,
+    sum: AtomicU64,
+    count: AtomicUsize,
+    updated_at: AtomicU64,
+    unit: Option<Unit>,
+    epsilon: f64,
+    quantiles: Vec<f64>,
+    sketch: Mutex<Vec<(f64, f64, f64)>>,
 }
 
-fn format_labels<const N: usize>(keys: [&str; N], labels: [String; N]) -> String {
-    if N > 0 {
-        let pairs: Vec<String> = keys
-            .iter()
-            .zip(labels)
-            .map(|(key, label)| format!("{key}=\"{label}\""))
-            .collect();
-        let pairs = pairs.join(",").to_string();
-        format!("{{{pairs}}}")
-    } else {
+/// Re-registers `state` under a name carrying `unit`'s canonical suffix
+/// (e.g. `_seconds`), since the registry key is derived from the name.
+fn apply_unit(mut state: Box<State>, unit: Unit) -> Box<State> {
+    let mut registry = get_metrics().write().expect("registry must be valid");
+    registry.unregister(&state);
+    let suffix = format!("_{}", unit.name());
+    if !state.name.ends_with(&suffix) {
+        state.name = format!("{}{suffix}", state.name);
+    }
+    state.key = format!("{}{}", state.name, braced(&state.labels));
+    state.unit = Some(unit);
+    registry.register(&mut state);
+    state
+}
+
+/// Nanoseconds since an arbitrary process-local epoch, from a monotonic clock
+/// so wall-clock adjustments can't affect idle-timeout culling.
+fn now_nanos() -> u64 {
+    static INCEPTION: OnceLock<Instant> = OnceLock::new();
+    INCEPTION.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
+fn touch(state: &State) {
+    state.updated_at.store(now_nanos(), Ordering::Relaxed);
+}
+
+fn load_f64(atomic: &AtomicU64) -> f64 {
+    f64::from_bits(atomic.load(Ordering::Relaxed))
+}
+
+fn store_f64(atomic: &AtomicU64, value: f64) {
+    atomic.store(value.to_bits(), Ordering::Relaxed);
+}
+
+/// The multiplier to apply to a raw value before storing it, so that e.g. a
+/// gauge declared `with_unit(Unit::Kibibytes)` records actual bytes rather
+/// than silently conflating KiB with the base unit.
+fn scale_of(state: &State) -> f64 {
+    state.unit.map_or(1.0, Unit::scale)
+}
+
+/// Applies `f` to the value stored in `atomic` and writes back the result,
+/// retrying on concurrent writers. `AtomicU64` has no native float op, so this
+/// is the standard compare-and-swap loop for a `fetch_add`/`fetch_max` over a
+/// bit-encoded `f64`.
+fn update_f64(atomic: &AtomicU64, f: impl Fn(f64) -> f64) {
+    let mut current = atomic.load(Ordering::Relaxed);
+    loop {
+        let next = f(f64::from_bits(current)).to_bits();
+        match atomic.compare_exchange_weak(current, next, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => break,
+            Err(actual) => current = actual,
+        }
+    }
+}
+
+fn format_label_pairs<const N: usize>(keys: [&str; N], labels: [String; N]) -> String {
+    keys.iter()
+        .zip(labels)
+        .map(|(key, label)| format!("{key}=\"{label}\""))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn braced(pairs: &str) -> String {
+    if pairs.is_empty() {
         String::new()
+    } else {
+        format!("{{{pairs}}}")
     }
 }
 
 impl Drop for State {
     fn drop(&mut self) {
         let mut registry = get_metrics().write().expect("registry must be valid");
-        registry.unregister(&self);
+        registry.unregister(self);
     }
 }
 
@@ -172,17 +600,172 @@ pub fn get_metrics() -> &'static RwLock<Registry> {
     SINGLETON.get_or_init(|| RwLock::new(Registry::new()))
 }
 
+/// Encodes metrics into an exposition text format, one metric at a time, in
+/// the order `Registry` iterates them. Implementations track the last-seen
+/// metric name internally so `# HELP`/`# TYPE` headers are only written once
+/// per name, matching `Registry`'s name-grouped iteration order.
+pub trait MetricEncoder {
+    fn encode(&mut self, state: &State, sink: &mut dyn fmt::Write) -> fmt::Result;
+
+    /// Called once after every metric has been encoded, for format-level
+    /// trailers (e.g. OpenMetrics' `# EOF`). No-op by default.
+    fn finish(&mut self, _sink: &mut dyn fmt::Write) -> fmt::Result {
+        Ok(())
+    }
+}
+
+fn kind_name(kind: &Kind) -> &'static str {
+    match kind {
+        Kind::Counter => "counter",
+        Kind::Gauge => "gauge",
+        Kind::Histogram => "histogram",
+        Kind::Summary => "summary",
+    }
+}
+
+fn write_metadata(metric: &State, sink: &mut dyn fmt::Write) -> fmt::Result {
+    let name = &metric.name;
+    if !metric.help.is_empty() {
+        writeln!(sink, "# HELP {name} {}", metric.help)?;
+    }
+    writeln!(sink, "# TYPE {name} {}", kind_name(&metric.kind))?;
+    if let Some(unit) = metric.unit {
+        writeln!(sink, "# UNIT {name} {}", unit.name())?;
+    }
+    Ok(())
+}
+
+fn write_histogram(metric: &State, name: &str, sink: &mut dyn fmt::Write) -> fmt::Result {
+    let prefix = if metric.labels.is_empty() {
+        String::new()
+    } else {
+        format!("{},", metric.labels)
+    };
+    let count = metric.count.load(Ordering::Relaxed);
+    for (bound, bucket) in metric.buckets.iter().zip(metric.bucket_counts.iter()) {
+        let bucket = bucket.load(Ordering::Relaxed);
+        writeln!(sink, "{name}_bucket{{{prefix}le=\"{bound}\"}} {bucket}")?;
+    }
+    writeln!(sink, "{name}_bucket{{{prefix}le=\"+Inf\"}} {count}")?;
+    let sum = load_f64(&metric.sum);
+    writeln!(sink, "{name}_sum{} {sum}", braced(&metric.labels))?;
+    writeln!(sink, "{name}_count{} {count}", braced(&metric.labels))
+}
+
+fn write_summary(metric: &State, name: &str, sink: &mut dyn fmt::Write) -> fmt::Result {
+    let prefix = if metric.labels.is_empty() {
+        String::new()
+    } else {
+        format!("{},", metric.labels)
+    };
+    for quantile in &metric.quantiles {
+        let value = quantile_from_sketch(metric, *quantile).unwrap_or(f64::NAN);
+        writeln!(sink, "{name}{{{prefix}quantile=\"{quantile}\"}} {value}")?;
+    }
+    let sum = load_f64(&metric.sum);
+    let count = metric.count.load(Ordering::Relaxed);
+    writeln!(sink, "{name}_sum{} {sum}", braced(&metric.labels))?;
+    writeln!(sink, "{name}_count{} {count}", braced(&metric.labels))
+}
+
+/// The legacy Prometheus text exposition format.
+#[derive(Default)]
+pub struct PrometheusEncoder {
+    current: String,
+}
+
+impl PrometheusEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MetricEncoder for PrometheusEncoder {
+    fn encode(&mut self, metric: &State, sink: &mut dyn fmt::Write) -> fmt::Result {
+        if metric.name != self.current {
+            write_metadata(metric, sink)?;
+            self.current = metric.name.clone();
+        }
+        match metric.kind {
+            Kind::Histogram => write_histogram(metric, &metric.name, sink),
+            Kind::Summary => write_summary(metric, &metric.name, sink),
+            Kind::Counter | Kind::Gauge => {
+                let value = load_f64(&metric.value);
+                writeln!(sink, "{} {value}", metric.key)
+            }
+        }
+    }
+}
+
+/// The strict OpenMetrics text format: counters carry the `_total` suffix and
+/// the stream is terminated with a trailing `# EOF` line.
+#[derive(Default)]
+pub struct OpenMetricsEncoder {
+    current: String,
+}
+
+impl OpenMetricsEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MetricEncoder for OpenMetricsEncoder {
+    fn encode(&mut self, metric: &State, sink: &mut dyn fmt::Write) -> fmt::Result {
+        if metric.name != self.current {
+            write_metadata(metric, sink)?;
+            self.current = metric.name.clone();
+        }
+        match metric.kind {
+            Kind::Counter => {
+                let value = load_f64(&metric.value);
+                writeln!(
+                    sink,
+                    "{}_total{} {value}",
+                    metric.name,
+                    braced(&metric.labels)
+                )
+            }
+            Kind::Histogram => write_histogram(metric, &metric.name, sink),
+            Kind::Summary => write_summary(metric, &metric.name, sink),
+            Kind::Gauge => {
+                let value = load_f64(&metric.value);
+                writeln!(sink, "{} {value}", metric.key)
+            }
+        }
+    }
+
+    fn finish(&mut self, sink: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(sink, "# EOF")
+    }
+}
+
 pub struct Registry {
     metrics: BTreeMap<String, AtomicPtr<State>>,
+    idle_timeout: Option<Duration>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Registry {
     pub fn new() -> Self {
         Registry {
             metrics: Default::default(),
+            idle_timeout: None,
         }
     }
 
+    /// Once set, `encode_prometheus_report_culled` drops gauges, histograms and
+    /// summaries that haven't been updated within `idle_timeout`; counters are always kept,
+    /// since their cumulative value stays meaningful even while idle.
+    pub fn set_idle_timeout(&mut self, idle_timeout: Option<Duration>) {
+        self.idle_timeout = idle_timeout;
+    }
+
     fn register(&mut self, state: &mut Box<State>) {
         let ptr = AtomicPtr::new(state.as_mut());
         self.metrics.insert(state.key.clone(), ptr);
@@ -190,39 +773,61 @@ impl Registry {
 
     fn unregister(&mut self, state: &State) {
         if let Some(record) = self.metrics.get(&state.key) {
-            if record.load(Ordering::Relaxed) as *const _ != state as *const _ {
-                // new metric state with same key overwrites record via registration
-                // nothing to do, old state dropped
-                return;
-            } else {
+            if std::ptr::eq(record.load(Ordering::Relaxed), state) {
                 self.metrics.remove(&state.key);
             }
+            // else: new metric state with same key overwrote record via registration,
+            // nothing to do, old state dropped
         }
     }
 
     pub fn encode_prometheus_report(&self) -> String {
-        let mut current = String::new();
+        self.encode_with(&mut PrometheusEncoder::new(), false)
+    }
+
+    /// Same as `encode_prometheus_report`, but drops gauges, histograms and summaries whose
+    /// `idle_timeout` has elapsed, so scrape output stays bounded when label sets churn.
+    pub fn encode_prometheus_report_culled(&self) -> String {
+        self.encode_with(&mut PrometheusEncoder::new(), true)
+    }
+
+    /// Strict OpenMetrics text encoding: counters get the `_total` suffix and
+    /// the output ends with `# EOF`, per the OpenMetrics exposition format.
+    pub fn encode_openmetrics_report(&self) -> String {
+        self.encode_with(&mut OpenMetricsEncoder::new(), false)
+    }
+
+    /// Same as `encode_openmetrics_report`, but drops gauges, histograms and summaries whose
+    /// `idle_timeout` has elapsed, so scrape output stays bounded when label sets churn.
+    pub fn encode_openmetrics_report_culled(&self) -> String {
+        self.encode_with(&mut OpenMetricsEncoder::new(), true)
+    }
+
+    /// Encodes every registered metric through `encoder`, skipping idle gauges,
+    /// histograms and summaries when `cull` is set and `idle_timeout` is configured.
+    pub fn encode_with(&self, encoder: &mut dyn MetricEncoder, cull: bool) -> String {
+        let now = now_nanos();
         let mut output = String::new();
         for ptr in self.metrics.values() {
             let ptr = ptr.load(Ordering::Relaxed);
             // SAFETY: pointer is valid because metrics on drop removed from registry via RwLock
             let metric = unsafe { &*ptr };
-            let name = &metric.name;
-            let value = &metric.value;
-            let key = &metric.key;
-            if metric.name != current {
-                let kind = match metric.kind {
-                    Kind::Counter => "counter",
-                    Kind::Gauge => "gauge",
-                };
-                if !metric.help.is_empty() {
-                    output += &format!("# HELP {name} {}\n", metric.help);
+            if cull {
+                if let Some(idle_timeout) = self.idle_timeout {
+                    let idle = !matches!(metric.kind, Kind::Counter);
+                    let age = now.saturating_sub(metric.updated_at.load(Ordering::Relaxed));
+                    if idle && age > idle_timeout.as_nanos() as u64 {
+                        continue;
+                    }
                 }
-                output += &format!("# TYPE {name} {kind}\n");
-                current = name.clone();
             }
-            output += &format!("{key} {value}\n");
+            encoder
+                .encode(metric, &mut output)
+                .expect("encoding into a String never fails");
         }
+        encoder
+            .finish(&mut output)
+            .expect("encoding into a String never fails");
         output
     }
 }
@@ -235,9 +840,9 @@ struct MyMetrics {
 
 pub fn test_usage() {
     {
-        let mut m = MyMetrics {
+        let m = MyMetrics {
             metric_a: Counter::with_labels("metric", ["key"], ["a"]),
-            update_time: Gauge::new("metric_c"),
+            update_time: Gauge::new("metric_c").with_unit(Unit::Seconds),
             metric_b: Counter::with_labels("metric", ["key", "container"], ["b", "my_service"]),
         };
         loop {
@@ -282,6 +887,12 @@ pub struct Stopwatch {
     timestamp: Instant,
 }
 
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Stopwatch {
     pub fn new() -> Self {
         Stopwatch {
@@ -314,4 +925,78 @@ impl Stopwatch {
 // # HELP text_render_keys_count ...
 // # TYPE text_render_keys_count gauge
 // text_render_keys_count{key="buba"} 4
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_quantile_matches_uniform_distribution() {
+        let summary = Summary::new("test_summary_quantile");
+        for value in 1..=100 {
+            summary.observe(value as f64);
+        }
+        let median = summary.quantile(0.5).expect("summary has observations");
+        assert!(
+            (40.0..=60.0).contains(&median),
+            "expected quantile(0.5) near 50 for a uniform 1..=100 distribution, got {median}"
+        );
+    }
+
+    #[test]
+    fn counter_add_is_consistent_under_concurrent_writers() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let counter = Arc::new(Counter::new("test_counter_concurrent"));
+        let threads = 8;
+        let increments_per_thread = 1000;
+        let handles = (0..threads)
+            .map(|_| {
+                let counter = Arc::clone(&counter);
+                thread::spawn(move || {
+                    for _ in 0..increments_per_thread {
+                        counter.inc();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().expect("writer thread must not panic");
+        }
+        assert_eq!(counter.value(), threads * increments_per_thread);
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative_and_include_inf() {
+        let histogram =
+            Histogram::with_buckets("test_histogram_buckets", [], [0; 0], vec![1.0, 2.0]);
+        for value in [0.5, 1.0, 1.5, 3.0] {
+            histogram.observe(value);
+        }
+        let report = get_metrics()
+            .read()
+            .expect("registry must be valid to read")
+            .encode_prometheus_report();
+        assert!(report.contains("test_histogram_buckets_bucket{le=\"1\"} 2"));
+        assert!(report.contains("test_histogram_buckets_bucket{le=\"2\"} 3"));
+        assert!(report.contains("test_histogram_buckets_bucket{le=\"+Inf\"} 4"));
+        assert!(report.contains("test_histogram_buckets_count 4"));
+    }
+
+    #[test]
+    fn openmetrics_encoder_uses_total_suffix_and_trailing_eof() {
+        let counter = Counter::new("test_openmetrics_counter");
+        counter.add(3);
+        let report = get_metrics()
+            .read()
+            .expect("registry must be valid to read")
+            .encode_openmetrics_report();
+        assert!(report.contains("test_openmetrics_counter_total 3"));
+        assert!(
+            report.trim_end().ends_with("# EOF"),
+            "report must be terminated with a trailing # EOF line, got: {report:?}"
+        );
+    }
+}
 //